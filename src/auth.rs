@@ -0,0 +1,115 @@
+use crate::config::Config;
+use crate::cookies::{self, Session};
+use crate::netscape;
+
+use anyhow::{Context, Result};
+use reqwest_cookie_store::CookieStoreMutex;
+use std::env;
+use std::fs;
+use std::sync::Arc;
+
+/// A source of an authenticated cookie jar for `Downloader`. Backends differ
+/// only in how they seed the initial Kakao session; the SSO token exchange
+/// that turns it into Daum cookies is shared by [`Session`].
+#[async_trait::async_trait]
+pub trait AuthBackend {
+    async fn get_cookies(&self) -> Result<Arc<CookieStoreMutex>>;
+}
+
+/// Picks a backend based on `config`: credential login if a username and
+/// password are available (from the config file or the
+/// `DAUM_CAFE_USERNAME`/`DAUM_CAFE_PASSWORD` environment variables),
+/// otherwise the existing cookies-file export.
+pub fn backend_for(config: &Config) -> Box<dyn AuthBackend> {
+    let username = config
+        .username
+        .clone()
+        .or_else(|| env::var("DAUM_CAFE_USERNAME").ok());
+    let password = config
+        .password
+        .clone()
+        .or_else(|| env::var("DAUM_CAFE_PASSWORD").ok());
+
+    match (username, password) {
+        (Some(username), Some(password)) => Box::new(CredentialAuth::new(
+            username,
+            password,
+            config.cookies_file.clone(),
+        )),
+        _ => Box::new(CookieFileAuth::new(config.cookies_file.clone())),
+    }
+}
+
+/// Seeds the session from a Netscape `cookies.txt` export, keeping the
+/// unexpired cookies that match the Kakao and Daum login hosts.
+pub struct CookieFileAuth {
+    cookies_file: String,
+}
+
+impl CookieFileAuth {
+    pub fn new(cookies_file: String) -> Self {
+        Self { cookies_file }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthBackend for CookieFileAuth {
+    async fn get_cookies(&self) -> Result<Arc<CookieStoreMutex>> {
+        println!("Authenticating...");
+
+        let current_cookies_file = cookies::current_cookies_path(&self.cookies_file);
+        let jar = Arc::new(CookieStoreMutex::new(cookies::load_jar(
+            &current_cookies_file,
+        )));
+        let session = Session::new(Arc::clone(&jar))?;
+
+        let cookies_contents = fs::read_to_string(&self.cookies_file)
+            .context(format!("Error reading {}", &self.cookies_file))?;
+        let exported_cookies = netscape::parse_cookies_file(&cookies_contents);
+        session.seed_exported_cookies(&exported_cookies, &self.cookies_file)?;
+        session.finish_sso_exchange().await?;
+
+        println!("Authentication done");
+        Ok(jar)
+    }
+}
+
+/// Logs in with a Kakao account's username/password instead of requiring a
+/// pre-exported cookies file.
+pub struct CredentialAuth {
+    username: String,
+    password: String,
+    session_file: String,
+}
+
+impl CredentialAuth {
+    pub fn new(username: String, password: String, session_file: String) -> Self {
+        Self {
+            username,
+            password,
+            session_file,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthBackend for CredentialAuth {
+    async fn get_cookies(&self) -> Result<Arc<CookieStoreMutex>> {
+        println!("Authenticating with Kakao account...");
+
+        let current_cookies_file = cookies::current_cookies_path(&self.session_file);
+        let jar = Arc::new(CookieStoreMutex::new(cookies::load_jar(
+            &current_cookies_file,
+        )));
+        let session = Session::new(Arc::clone(&jar))?;
+
+        session
+            .login(&self.username, &self.password)
+            .await
+            .context("Error logging in with Kakao account")?;
+        session.finish_sso_exchange().await?;
+
+        println!("Authentication done");
+        Ok(jar)
+    }
+}
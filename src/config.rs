@@ -7,7 +7,14 @@ static CONFIG_FILE: &str = "config.toml";
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
+    /// Path to the exported/cached cookie state. Also used, regardless of
+    /// auth backend, to namespace the `.current` persistent cookie jar.
     pub cookies_file: String,
+    /// Kakao account credentials for logging in programmatically instead of
+    /// reading `cookies_file` as a browser export. Can also be supplied via
+    /// the `DAUM_CAFE_USERNAME`/`DAUM_CAFE_PASSWORD` environment variables.
+    pub username: Option<String>,
+    pub password: Option<String>,
     #[serde(default = "default_num_processes")]
     pub max_connections: usize,
     pub cafe: HashMap<String, CafeConfig>,
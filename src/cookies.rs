@@ -1,109 +1,173 @@
 use crate::error::DownloaderError;
+use crate::netscape::Cookie;
 
 use anyhow::{Context, Result};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
+use reqwest::Url;
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::Deserialize;
 use std::fs;
-use std::io::Write;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub struct Auth {
-    client: reqwest::Client,
+lazy_static! {
+    // The hosts the login flow talks to, in order: Kakao's account/SSO
+    // endpoints, then Daum's session exchange.
+    static ref LOGIN_URLS: Vec<Url> = vec![
+        Url::parse("https://accounts.kakao.com/").unwrap(),
+        Url::parse("https://logins.daum.net/").unwrap(),
+    ];
 }
 
-impl Auth {
-    pub async fn get_cookies(cookies_file: &str) -> Result<String> {
-        println!("Authenticating...");
+/// The Kakao/Daum login chain, shared by every [`crate::auth::AuthBackend`].
+/// A backend is only responsible for getting an initial Kakao session into
+/// the jar (by reading an export or logging in with credentials); from there
+/// the SSO token exchange is identical.
+pub struct Session {
+    client: reqwest::Client,
+    jar: Arc<CookieStoreMutex>,
+}
 
-        let auth = Auth {
+impl Session {
+    pub fn new(jar: Arc<CookieStoreMutex>) -> Result<Self> {
+        let client = reqwest::Client::builder()
             // Force native TLS because logins.daum.net doesn't support forward secrecy ciphers,
             // which rustls requires
-            client: reqwest::Client::builder()
-                .use_native_tls()
-                .build()
-                .context("Error building authentication client")?,
-        };
-
-        let current_cookies_file = format!("{}.current", cookies_file);
-
-        let kakao_cookies = auth
-            .read_cookies_file(cookies_file, &current_cookies_file)
-            .context(format!("Error reading {}", cookies_file))?;
-        let sso_token = match auth.get_sso_token(&kakao_cookies).await {
-            Ok(t) => t,
-            Err(_) => {
-                let new_kakao_cookies = auth
-                    .update_kakao_coookies(&deserialize_cookies(&kakao_cookies), &current_cookies_file)
-                    .await?;
-                auth.get_sso_token(&new_kakao_cookies)
-                    .await
-                    .context("Error getting SSO token")?
-            }
-        };
-        let daum_cookies = auth
-            .get_daum_cookies(sso_token.as_str())
-            .await
-            .context("Error getting Daum cookies")?;
+            .use_native_tls()
+            .cookie_provider(Arc::clone(&jar))
+            .build()
+            .context("Error building authentication client")?;
 
-        println!("Authentication done");
-        Ok(daum_cookies)
+        Ok(Session { client, jar })
     }
 
-    async fn get_daum_cookies(&self, sso_token: &str) -> Result<String> {
-        // Get daum.net cookies
-        let resp = self
+    /// Logs in with a Kakao account's `username`/`password`, leaving the
+    /// resulting session cookies in the jar.
+    pub async fn login(&self, username: &str, password: &str) -> Result<()> {
+        // Prime the login page and grab the CSRF token Kakao expects echoed
+        // back in the submit, not just carried by its cookie.
+        let login_page = self
             .client
-            .get("https://logins.daum.net/accounts/kakaossotokenlogin.do")
-            .query(&[("ssotoken", sso_token)])
-            .header(reqwest::header::HOST, "logins.daum.net")
+            .get("https://accounts.kakao.com/login")
             .send()
+            .await?
+            .text()
             .await?;
 
-        // Extract daum.net cookies
-        let cookies = resp
-            .headers()
-            .get_all(reqwest::header::SET_COOKIE)
-            .iter()
-            .filter_map(|v| v.to_str().ok())
-            .collect::<Vec<_>>()
-            .join("; ");
+        lazy_static! {
+            static ref CSRF_RE: Regex =
+                Regex::new(r#"name="csrfToken"\s+value="(?P<token>[^"]+)""#).unwrap();
+        }
+        let csrf_token = CSRF_RE
+            .captures(&login_page)
+            .and_then(|c| c.name("token"))
+            .ok_or(DownloaderError::Authentication)?
+            .as_str();
 
-        Ok(cookies)
-    }
+        #[derive(Deserialize)]
+        struct LoginResponse {
+            status: i32,
+        }
 
-    fn read_cookies_file(&self, cookies_file: &str, current_cookies_file: &str) -> Result<String> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(
-                r"(?m)^(?P<domain>\.kakao\.com)\t.+?\t.+?\t.+?\t.+?\t(?P<name>.+?)\t(?P<value>.+?)$"
-            )
-            .unwrap();
+        let login: LoginResponse = self
+            .client
+            .post("https://accounts.kakao.com/weblogin/authenticate.json")
+            .form(&[
+                ("email", username),
+                ("password", password),
+                ("csrfToken", csrf_token),
+                (
+                    "continue",
+                    "https://logins.daum.net/accounts/kakaoconnector.do",
+                ),
+            ])
+            .header(reqwest::header::REFERER, "https://accounts.kakao.com/login")
+            .header("Csrf-Token", csrf_token)
+            .send()
+            .await?
+            .json()
+            .await
+            .context("Error parsing Kakao login response")?;
+
+        if login.status != 0 {
+            return Err(DownloaderError::Authentication.into());
         }
 
-        if let Ok(current_cookies) = fs::read_to_string(current_cookies_file) {
-            return Ok(current_cookies);
+        // A status of 0 with no session cookie actually landing in the jar
+        // would otherwise only surface later as a confusing SSO-token
+        // failure, so confirm one is present before declaring success. Ask
+        // the store whether it would actually send anything to the login
+        // URL, rather than keying on the raw (often absent) Domain
+        // attribute, since that's the same effective-domain logic it uses
+        // to decide what to send on every other request.
+        let has_session_cookie = {
+            let store = self
+                .jar
+                .lock()
+                .map_err(|_| DownloaderError::Authentication)?;
+            LOGIN_URLS
+                .iter()
+                .any(|url| store.get_request_values(url).next().is_some())
+        };
+        if !has_session_cookie {
+            return Err(DownloaderError::Authentication.into());
         }
 
-        let cookies_contents = fs::read_to_string(&cookies_file)?;
+        Ok(())
+    }
+
+    /// Seeds the jar with cookies exported from a browser so the first
+    /// request to each host the login flow touches has a session to present.
+    ///
+    /// Expired cookies are skipped and reported so the user knows to refresh
+    /// the export.
+    pub fn seed_exported_cookies(&self, cookies: &[Cookie], cookies_file: &str) -> Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
 
-        let cookie: String = RE
-            .captures_iter(&cookies_contents)
-            .filter_map(|c| {
-                let name = c.name("name")?.as_str();
-                let value = c.name("value")?.as_str();
-                Some(format!("{}={}", name, value))
-            })
-            .collect::<Vec<_>>()
-            .join("; ");
+        let mut store = self
+            .jar
+            .lock()
+            .map_err(|_| DownloaderError::Authentication)?;
+        let mut expired = Vec::new();
 
-        Ok(cookie)
+        for cookie in cookies {
+            if cookie.is_expired(now) {
+                expired.push(cookie.name.as_str());
+                continue;
+            }
+
+            for url in LOGIN_URLS.iter() {
+                if !cookie.matches_url(url) {
+                    continue;
+                }
+                let raw = format!("{}={}", cookie.name, cookie.value);
+                if let Ok(raw_cookie) = reqwest_cookie_store::RawCookie::parse(raw) {
+                    store.insert_raw(&raw_cookie, url).ok();
+                }
+            }
+        }
+
+        if !expired.is_empty() {
+            eprintln!(
+                "Warning: {} has expired cookies, please re-export it: {}",
+                cookies_file,
+                expired.join(", ")
+            );
+        }
+
+        Ok(())
     }
 
-    async fn get_sso_token(&self, kakao_cookies: &str) -> Result<String> {
+    pub async fn get_sso_token(&self) -> Result<String> {
         // Get SSO token
         let resp = self
             .client
             .get("https://accounts.kakao.com/weblogin/sso_token/daum.js?callback=loginByToken")
-            .header(reqwest::header::COOKIE, kakao_cookies)
             .header(reqwest::header::REFERER, "https://logins.daum.net/")
             .send()
             .await?
@@ -124,72 +188,90 @@ impl Auth {
         Ok(token.into())
     }
 
-    async fn update_kakao_coookies(
-        &self,
-        kakao_cookies: &HashMap<String, String>,
-        current_cookies_file: &str,
-    ) -> Result<String> {
+    pub async fn refresh_kakao_session(&self) -> Result<()> {
         println!("Updating cookies");
 
-        let resp = self
-            .client
+        // Hitting the account info page causes Kakao to rotate its session
+        // cookies via Set-Cookie, which the jar picks up automatically.
+        self.client
             .get("https://accounts.kakao.com/weblogin/account/info")
-            .header(reqwest::header::COOKIE, serialize_cookies(kakao_cookies))
             .header(reqwest::header::REFERER, "https://accounts.kakao.com/")
             .send()
             .await?;
 
-        let new_cookies = resp
-            .headers()
-            .get_all(reqwest::header::SET_COOKIE)
-            .iter()
-            .filter_map(|v| {
-                let mut cookie = v.to_str().ok()?.split(';');
-                let name = cookie.next()?.to_owned();
-                let value = cookie.next().unwrap_or("").to_owned();
-                Some((name, value))
-            })
-            .collect::<HashMap<_, _>>();
-
-        let mut final_cookies: HashMap<String, String> = kakao_cookies.clone();
-        for (new_k, new_v) in new_cookies {
-            if let Some(old_v) = final_cookies.get_mut(&new_k) {
-                if new_v.is_empty() {
-                    final_cookies.remove(&new_k);
-                } else {
-                    *old_v = new_v;
-                }
-            }
-        }
+        Ok(())
+    }
 
-        let cookies = serialize_cookies(&final_cookies);
+    pub async fn get_daum_cookies(&self, sso_token: &str) -> Result<()> {
+        // The response's Set-Cookie headers are absorbed automatically by the
+        // cookie jar, so there's nothing to extract here.
+        self.client
+            .get("https://logins.daum.net/accounts/kakaossotokenlogin.do")
+            .query(&[("ssotoken", sso_token)])
+            .header(reqwest::header::HOST, "logins.daum.net")
+            .send()
+            .await?;
 
-        let mut file = fs::File::create(current_cookies_file)
-            .context("Unable to create current cookies file")?;
-        file.write_all(cookies.as_bytes())
-            .context("Unable to write to current cookies file")?;
+        Ok(())
+    }
 
-        Ok(cookies)
+    /// Exchanges an already-established Kakao session for Daum cookies,
+    /// retrying once after refreshing the Kakao session if the SSO token
+    /// request fails. Shared by every backend once it has a Kakao session in
+    /// the jar, so the two paths can't drift.
+    pub async fn finish_sso_exchange(&self) -> Result<()> {
+        let sso_token = match self.get_sso_token().await {
+            Ok(t) => t,
+            Err(_) => {
+                self.refresh_kakao_session().await?;
+                self.get_sso_token()
+                    .await
+                    .context("Error getting SSO token")?
+            }
+        };
+        self.get_daum_cookies(sso_token.as_str())
+            .await
+            .context("Error getting Daum cookies")?;
+
+        Ok(())
     }
 }
 
-fn serialize_cookies(map: &HashMap<String, String>) -> String {
-    let cookies = map
-        .iter()
-        .map(|(k, v)| format!("{}={}", k, v))
-        .collect::<Vec<_>>()
-        .join("; ");
-    cookies
+pub fn current_cookies_path(cookies_file: &str) -> String {
+    format!("{}.current", cookies_file)
+}
+
+/// Loads the persisted cookie jar from `path`, falling back to an empty jar
+/// if it doesn't exist. Warns (rather than silently discarding the session)
+/// if the file exists but isn't valid jar JSON, e.g. a `.current` file left
+/// over from before the jar was persisted as JSON.
+pub fn load_jar(path: &str) -> reqwest_cookie_store::CookieStore {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => return reqwest_cookie_store::CookieStore::default(),
+    };
+
+    match reqwest_cookie_store::CookieStore::load_json(BufReader::new(file)) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!(
+                "Warning: could not load cached session from {}, starting a fresh one: {}",
+                path, e
+            );
+            reqwest_cookie_store::CookieStore::default()
+        }
+    }
 }
 
-fn deserialize_cookies(cookies: &str) -> HashMap<String, String> {
-    cookies
-        .split(';')
-        .filter_map(|s| {
-            let mut cookie = s.split('=');
-            let name = cookie.next()?.trim().to_owned();
-            let value = cookie.next()?.trim().to_owned();
-            Some((name, value))
-        })
-        .collect()
+/// Persists the jar to `path` as JSON so a resumed run picks up any cookies
+/// that were rotated mid-session.
+pub fn save_jar(jar: &CookieStoreMutex, path: &str) -> Result<()> {
+    let store = jar.lock().map_err(|_| DownloaderError::Authentication)?;
+    let mut file = fs::File::create(path).context("Unable to create current cookies file")?;
+    store
+        .save_json(&mut file)
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Unable to write to current cookies file")?;
+
+    Ok(())
 }
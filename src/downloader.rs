@@ -1,11 +1,16 @@
+use crate::auth;
 use crate::config::Config;
-use crate::cookies::Auth;
+use crate::cookies;
 
 use anyhow::Result;
 
 pub async fn download(config: &Config) -> Result<()> {
-    let cookies = Auth::new()?.get_cookies(&config.cookies_file).await?;
-    downloader::download(&config, cookies).await?;
+    let jar = auth::backend_for(config).get_cookies().await?;
+    downloader::download(&config, &jar).await?;
+
+    // Save once the whole run is done, not right after authenticating, so
+    // this also captures any cookies Daum rotates mid-run.
+    cookies::save_jar(&jar, &cookies::current_cookies_path(&config.cookies_file))?;
 
     Ok(())
 }
@@ -17,13 +22,15 @@ mod downloader {
     use anyhow::Result;
     use indicatif::{ProgressBar, ProgressStyle};
     use lazy_static::lazy_static;
+    use reqwest_cookie_store::CookieStoreMutex;
     use serde::Deserialize;
     use std::fs::{self, File};
     use std::io::prelude::*;
     use std::path::Path;
+    use std::sync::Arc;
 
-    pub async fn download(config: &Config, cookies: String) -> Result<()> {
-        let downloader = Downloader::new(&config, cookies);
+    pub async fn download(config: &Config, jar: &Arc<CookieStoreMutex>) -> Result<()> {
+        let downloader = Downloader::new(&config, jar);
         downloader.download_all().await
     }
 
@@ -81,15 +88,15 @@ mod downloader {
     }
 
     impl<'a> Downloader<'a> {
-        fn new(config: &Config, cookies: String) -> Downloader {
-            let mut headers = reqwest::header::HeaderMap::new();
-            headers.insert(reqwest::header::COOKIE, cookies.parse().unwrap());
-
+        fn new(config: &'a Config, jar: &Arc<CookieStoreMutex>) -> Downloader<'a> {
             let client_auth = reqwest::Client::builder()
-                .default_headers(headers)
+                .cookie_provider(Arc::clone(jar))
+                .build()
+                .unwrap();
+            let client = reqwest::Client::builder()
+                .cookie_provider(Arc::clone(jar))
                 .build()
                 .unwrap();
-            let client = reqwest::Client::new();
             Downloader {
                 client_auth,
                 client,
@@ -139,7 +146,7 @@ mod downloader {
             // Download newer posts
             for id in first_id..=latest_id {
                 // Query Daum API
-                let api_url = format!("http://api.m.cafe.daum.net/mcafe/api/v1/hybrid/{}/{}/{}?ref=&isSimple=false&installedVersion=3.15.1", &cafe_name, &cafe_board, id);
+                let api_url = format!("https://api.m.cafe.daum.net/mcafe/api/v1/hybrid/{}/{}/{}?ref=&isSimple=false&installedVersion=3.15.1", &cafe_name, &cafe_board, id);
                 let resp = self
                     .client_auth
                     .get(api_url)
@@ -3,10 +3,12 @@ use crate::downloader::Downloader;
 use anyhow::Result;
 use std::process;
 
+mod auth;
 mod config;
 mod cookies;
 mod downloader;
 mod error;
+mod netscape;
 
 #[tokio::main]
 async fn main() {
@@ -0,0 +1,188 @@
+use reqwest::Url;
+
+/// A single parsed row from a Netscape/Mozilla `cookies.txt` export.
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    pub expires: i64,
+    pub name: String,
+    pub value: String,
+}
+
+impl Cookie {
+    /// `expires == 0` marks a session cookie, which never expires on its own.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires != 0 && self.expires < now
+    }
+
+    /// Whether this cookie would be sent on a request to `url`, following the
+    /// usual scheme/host/path matching rules.
+    pub fn matches_url(&self, url: &Url) -> bool {
+        if self.secure && url.scheme() != "https" {
+            return false;
+        }
+
+        let host = match url.host_str() {
+            Some(h) => h,
+            None => return false,
+        };
+        let domain = self.domain.trim_start_matches('.');
+        let host_matches = if self.include_subdomains || self.domain.starts_with('.') {
+            host == domain || host.ends_with(&format!(".{}", domain))
+        } else {
+            host == domain
+        };
+        if !host_matches {
+            return false;
+        }
+
+        let path = url.path();
+        let cookie_path = self.path.trim_end_matches('/');
+        path == cookie_path || path.starts_with(&format!("{}/", cookie_path))
+    }
+}
+
+/// Parses the contents of a Netscape/Mozilla `cookies.txt` export into
+/// individual cookies.
+///
+/// Each non-comment line has seven tab-separated fields: `domain`,
+/// `include_subdomains`, `path`, `secure`, `expires`, `name`, `value`. Lines
+/// starting with `#` are comments, except for the `#HttpOnly_` prefix, which
+/// marks an HttpOnly cookie and is stripped before parsing.
+pub fn parse_cookies_file(contents: &str) -> Vec<Cookie> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                return None;
+            }
+            parse_line(line)
+        })
+        .collect()
+}
+
+fn parse_line(line: &str) -> Option<Cookie> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 7 {
+        return None;
+    }
+
+    Some(Cookie {
+        domain: fields[0].to_owned(),
+        include_subdomains: fields[1].eq_ignore_ascii_case("TRUE"),
+        path: fields[2].to_owned(),
+        secure: fields[3].eq_ignore_ascii_case("TRUE"),
+        expires: fields[4].parse().ok()?,
+        name: fields[5].to_owned(),
+        value: fields[6].to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_strips_http_only_prefix_and_skips_comments() {
+        let contents = "\
+# Netscape HTTP Cookie File
+#HttpOnly_.kakao.com\tTRUE\t/\tTRUE\t0\tsession\tabc123
+
+.daum.net\tTRUE\t/\tFALSE\t0\tother\txyz789
+";
+        let cookies = parse_cookies_file(contents);
+
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].domain, ".kakao.com");
+        assert_eq!(cookies[0].name, "session");
+        assert_eq!(cookies[0].value, "abc123");
+        assert_eq!(cookies[1].domain, ".daum.net");
+    }
+
+    fn session_cookie() -> Cookie {
+        Cookie {
+            domain: ".kakao.com".to_owned(),
+            include_subdomains: true,
+            path: "/".to_owned(),
+            secure: false,
+            expires: 0,
+            name: "session".to_owned(),
+            value: "abc123".to_owned(),
+        }
+    }
+
+    #[test]
+    fn is_expired_treats_zero_as_session_cookie() {
+        let cookie = session_cookie();
+        assert!(!cookie.is_expired(1_900_000_000));
+    }
+
+    #[test]
+    fn is_expired_compares_against_now() {
+        let mut cookie = session_cookie();
+        cookie.expires = 1_000;
+
+        assert!(!cookie.is_expired(500));
+        assert!(cookie.is_expired(1_500));
+    }
+
+    #[test]
+    fn matches_url_allows_subdomain_when_flagged() {
+        let cookie = session_cookie();
+        let url = Url::parse("http://accounts.kakao.com/login").unwrap();
+
+        assert!(cookie.matches_url(&url));
+    }
+
+    #[test]
+    fn matches_url_rejects_other_domains() {
+        let cookie = session_cookie();
+        let url = Url::parse("http://daum.net/").unwrap();
+
+        assert!(!cookie.matches_url(&url));
+    }
+
+    #[test]
+    fn matches_url_requires_exact_host_without_subdomain_flag() {
+        let mut cookie = session_cookie();
+        cookie.domain = "kakao.com".to_owned();
+        cookie.include_subdomains = false;
+
+        assert!(cookie.matches_url(&Url::parse("http://kakao.com/").unwrap()));
+        assert!(!cookie.matches_url(&Url::parse("http://accounts.kakao.com/").unwrap()));
+    }
+
+    #[test]
+    fn matches_url_rejects_secure_cookie_over_plain_http() {
+        let mut cookie = session_cookie();
+        cookie.secure = true;
+
+        assert!(!cookie.matches_url(&Url::parse("http://accounts.kakao.com/").unwrap()));
+        assert!(cookie.matches_url(&Url::parse("https://accounts.kakao.com/").unwrap()));
+    }
+
+    #[test]
+    fn matches_url_requires_path_prefix() {
+        let mut cookie = session_cookie();
+        cookie.path = "/weblogin".to_owned();
+
+        assert!(cookie.matches_url(&Url::parse("http://accounts.kakao.com/weblogin").unwrap()));
+        assert!(cookie.matches_url(&Url::parse("http://accounts.kakao.com/weblogin/sso").unwrap()));
+        assert!(!cookie.matches_url(&Url::parse("http://accounts.kakao.com/other").unwrap()));
+    }
+
+    #[test]
+    fn matches_url_path_prefix_respects_segment_boundary() {
+        let mut cookie = session_cookie();
+        cookie.path = "/foo".to_owned();
+
+        // `/foobar` starts with `/foo` as a string, but isn't under the `/foo`
+        // path as a URL, so it must not match.
+        assert!(!cookie.matches_url(&Url::parse("http://accounts.kakao.com/foobar").unwrap()));
+        assert!(cookie.matches_url(&Url::parse("http://accounts.kakao.com/foo/bar").unwrap()));
+    }
+}